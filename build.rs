@@ -1,16 +1,221 @@
+use std::env;
+use std::fs;
 use std::process::Command;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use autotools;
+use pkg_config;
+
+mod emit;
+
+use emit::{LibKind, SearchKind};
+
+/// Env vars `main` reads, watched with `cargo:rerun-if-env-changed` so the
+/// autotools build re-runs whenever the selected mode changes.
+const WATCHED_ENV_VARS: &[&str] = &[
+    "MYPKG_LIB_DIR",
+    "MYPKG_INCLUDE_DIR",
+    "MYPKG_LINK_KIND",
+    "MYPKG_LINK_MODIFIERS",
+    "MYPKG_SELF_CONTAINED",
+];
+
+/// File names under `mypkg/` (besides `*.c`/`*.h`) that should trigger a
+/// rebuild when touched.
+const WATCHED_SOURCE_NAMES: &[&str] = &["configure.ac", "Makefile.am", "configure"];
+
+/// Recursively emits `cargo:rerun-if-changed` for every `*.c`/`*.h` file
+/// under `dir`, plus the autotools input/output files by name.
+fn rerun_if_source_changed(dir: &Path) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            rerun_if_source_changed(&path);
+            continue;
+        }
+
+        let is_watched = path
+            .extension()
+            .map(|ext| ext == "c" || ext == "h")
+            .unwrap_or(false)
+            || path
+                .file_name()
+                .map(|name| WATCHED_SOURCE_NAMES.iter().any(|watched| name == *watched))
+                .unwrap_or(false);
+
+        if is_watched {
+            emit::rerun_if_changed(&path);
+        }
+    }
+}
+
+/// Reads `MYPKG_LINK_MODIFIERS` (a comma-separated list of `+`/`-`
+/// prefixed modifier names, e.g. `+whole-archive,-bundle`) and returns
+/// only the modifiers we recognize, in the order given.
+fn link_modifiers() -> Vec<String> {
+    let raw = match env::var("MYPKG_LINK_MODIFIERS") {
+        Ok(v) if !v.trim().is_empty() => v,
+        _ => return Vec::new(),
+    };
+
+    raw.split(',')
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .filter_map(|m| {
+            if !m.starts_with(['+', '-']) {
+                println!("cargo:warning=ignoring link modifier `{}` missing a +/- sign", m);
+                return None;
+            }
+            let name = &m[1..];
+            if emit::KNOWN_MODIFIERS.contains(&name) {
+                Some(m.to_string())
+            } else {
+                println!("cargo:warning=ignoring unknown link modifier `{}`", m);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Chooses the `rustc-link-lib` kind for the bundled build:
+/// `MYPKG_LINK_KIND` selects between `Static` (the default), `Dylib`,
+/// and `Framework`.
+fn link_kind() -> LibKind {
+    match env::var("MYPKG_LINK_KIND").as_deref() {
+        Ok("dylib") => LibKind::Dylib,
+        Ok("framework") => LibKind::Framework,
+        Ok("static") | Err(_) => LibKind::Static,
+        Ok(other) => {
+            println!("cargo:warning=unknown MYPKG_LINK_KIND `{}`, defaulting to static", other);
+            LibKind::Static
+        }
+    }
+}
+
+/// Whether the user wants `libmypkg.so` to be resolvable at runtime
+/// without relying on `LD_LIBRARY_PATH` or the system loader's default
+/// search path (`MYPKG_SELF_CONTAINED`, default on). A build script has
+/// no way to embed an rpath into the final binary it ends up linked into
+/// — `cargo:rustc-link-arg` only attaches to this crate's own `rustc`
+/// invocation, which never reaches the system linker for a `-sys`-style
+/// crate — so when this is on we can only warn and point at the place
+/// that *can* set one: the crate producing the final binary, via its own
+/// build script or `RUSTFLAGS="-C link-arg=-Wl,-rpath,...".`
+fn self_contained() -> bool {
+    !matches!(env::var("MYPKG_SELF_CONTAINED").as_deref(), Ok("0") | Ok("false"))
+}
+
+/// Candidate library directories under an autotools install prefix,
+/// covering the usual multilib/cross-compile layouts (`lib`, `lib64`,
+/// `lib/$TARGET`).
+fn candidate_lib_dirs(prefix: &Path) -> Vec<PathBuf> {
+    let mut candidates = vec![prefix.join("lib"), prefix.join("lib64")];
+    if let Ok(target) = env::var("TARGET") {
+        candidates.push(prefix.join("lib").join(target));
+    }
+    candidates
+}
+
+/// Whether `dir` contains a `libmypkg.*` file (static archive or shared
+/// object). A `mypkg.framework` bundle does not match.
+fn contains_mypkg_lib(dir: &Path) -> bool {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return false,
+    };
+
+    entries.flatten().any(|entry| {
+        entry
+            .file_name()
+            .to_str()
+            .map(|name| name.starts_with("libmypkg."))
+            .unwrap_or(false)
+    })
+}
+
+/// Emits a `rustc-link-search` line for every candidate directory under
+/// `prefix` that actually holds a `libmypkg.*` file, so multilib and
+/// cross-compiled installs are found when `--libdir` put them in one of
+/// those candidates; other directories are not searched. Uses the `framework` search kind for `LibKind::Framework` and
+/// `native` otherwise.
+fn emit_lib_search_dirs(prefix: &Path, kind: LibKind) {
+    let search_kind = if kind == LibKind::Framework {
+        SearchKind::Framework
+    } else {
+        SearchKind::Native
+    };
+
+    for dir in candidate_lib_dirs(prefix) {
+        if contains_mypkg_lib(&dir) {
+            emit::link_search(search_kind, &dir);
+        }
+    }
+}
+
+/// Looks for an already-installed `mypkg` so we can skip rebuilding the
+/// bundled C sources entirely. Mirrors the gio-sys/gtk-sys build scripts:
+/// `MYPKG_LIB_DIR`/`MYPKG_INCLUDE_DIR` are checked first, then a
+/// `pkg-config` probe. Returns `true` (having already emitted the link
+/// directives) if either source found the library.
+fn try_system_mypkg() -> bool {
+    if let Ok(lib_dir) = env::var("MYPKG_LIB_DIR") {
+        emit::link_search(SearchKind::Native, Path::new(&lib_dir));
+        emit::link_lib(LibKind::Static, &link_modifiers(), "mypkg");
+        if let Ok(include_dir) = env::var("MYPKG_INCLUDE_DIR") {
+            println!("cargo:include={}", include_dir);
+        }
+        return true;
+    }
+
+    match pkg_config::Config::new().probe("mypkg") {
+        Ok(library) => {
+            for path in &library.link_paths {
+                emit::link_search(SearchKind::Native, path);
+            }
+            emit::link_lib(LibKind::Dylib, &[], "mypkg");
+            true
+        }
+        Err(_) => false,
+    }
+}
 
 fn main() {
 
+    for var in WATCHED_ENV_VARS {
+        emit::rerun_if_env_changed(var);
+    }
+
+    if try_system_mypkg() {
+        return;
+    }
+
+    rerun_if_source_changed(Path::new("mypkg"));
+
     if !Path::new("mypkg/configure").exists() {
         Command::new("autoreconf").args(&["-i", "mypkg"]).status().unwrap();
     }
 
-    let dst = autotools::build("mypkg");
+    let kind = link_kind();
+    let mut config = autotools::Config::new("mypkg");
+    if kind == LibKind::Static {
+        config.disable("shared", None);
+    } else {
+        config.enable("shared", None);
+    }
+    let dst = config.build();
 
-    println!("cargo:rustc-link-search=native={}/lib", dst.display());
-    println!("cargo:rustc-link-lib=static=mypkg");
+    emit_lib_search_dirs(&dst, kind);
+    if kind == LibKind::Dylib && self_contained() {
+        println!(
+            "cargo:warning=MYPKG_SELF_CONTAINED is on (the default), but this build script cannot embed an \
+             rpath into the final binary's link line; set one in the crate that produces the \
+             final binary instead (its own build script, or RUSTFLAGS=\"-C link-arg=-Wl,-rpath,...\")"
+        );
+    }
+    emit::link_lib(kind, &link_modifiers(), "mypkg");
 }
@@ -0,0 +1,119 @@
+//! Typed Cargo build-script directive emitter.
+//!
+//! Replaces ad-hoc `println!("cargo:...")` calls with small enums and
+//! formatting helpers, so a typo in a directive string can't silently
+//! produce broken build metadata, and so the formatting is unit-testable.
+
+use std::fmt;
+use std::path::Path;
+
+/// Native-library modifiers (RFC 2951) that we know how to pass through.
+pub const KNOWN_MODIFIERS: &[&str] = &["whole-archive", "bundle", "verbatim"];
+
+/// The `kind` portion of a `cargo:rustc-link-lib` directive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LibKind {
+    Static,
+    Dylib,
+    Framework,
+}
+
+impl fmt::Display for LibKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LibKind::Static => "static",
+            LibKind::Dylib => "dylib",
+            LibKind::Framework => "framework",
+        })
+    }
+}
+
+/// The `kind` portion of a `cargo:rustc-link-search` directive. Trimmed
+/// to the two kinds we actually emit; add `dependency`/`all` back if a
+/// caller needs them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchKind {
+    Native,
+    Framework,
+}
+
+impl fmt::Display for SearchKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SearchKind::Native => "native",
+            SearchKind::Framework => "framework",
+        })
+    }
+}
+
+/// Formats the value side of a `cargo:rustc-link-lib` directive. Falls
+/// back to the plain `<kind>=<name>` form when no modifiers are given so
+/// older toolchains keep working.
+pub fn link_lib_spec(kind: LibKind, modifiers: &[String], name: &str) -> String {
+    if modifiers.is_empty() {
+        format!("{}={}", kind, name)
+    } else {
+        format!("{}:{}={}", kind, modifiers.join(","), name)
+    }
+}
+
+/// Emits `cargo:rustc-link-lib=<kind>[:modifiers]=<name>`.
+pub fn link_lib(kind: LibKind, modifiers: &[String], name: &str) {
+    println!("cargo:rustc-link-lib={}", link_lib_spec(kind, modifiers, name));
+}
+
+/// Emits `cargo:rustc-link-search=<kind>=<path>`.
+pub fn link_search(kind: SearchKind, path: &Path) {
+    println!("cargo:rustc-link-search={}={}", kind, path.display());
+}
+
+/// Emits `cargo:rerun-if-changed=<path>`.
+pub fn rerun_if_changed(path: &Path) {
+    println!("cargo:rerun-if-changed={}", path.display());
+}
+
+/// Emits `cargo:rerun-if-env-changed=<var>`.
+pub fn rerun_if_env_changed(var: &str) {
+    println!("cargo:rerun-if-env-changed={}", var);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn static_with_whole_archive_renders_exactly() {
+        let modifiers = vec!["+whole-archive".to_string()];
+        assert_eq!(
+            link_lib_spec(LibKind::Static, &modifiers, "mypkg"),
+            "static:+whole-archive=mypkg"
+        );
+    }
+
+    #[test]
+    fn no_modifiers_falls_back_to_plain_form() {
+        assert_eq!(link_lib_spec(LibKind::Static, &[], "mypkg"), "static=mypkg");
+    }
+
+    #[test]
+    fn multiple_modifiers_are_comma_joined() {
+        let modifiers = vec!["+whole-archive".to_string(), "+bundle".to_string()];
+        assert_eq!(
+            link_lib_spec(LibKind::Dylib, &modifiers, "mypkg"),
+            "dylib:+whole-archive,+bundle=mypkg"
+        );
+    }
+
+    #[test]
+    fn lib_kind_display_matches_cargo_vocabulary() {
+        assert_eq!(LibKind::Static.to_string(), "static");
+        assert_eq!(LibKind::Dylib.to_string(), "dylib");
+        assert_eq!(LibKind::Framework.to_string(), "framework");
+    }
+
+    #[test]
+    fn search_kind_display_matches_cargo_vocabulary() {
+        assert_eq!(SearchKind::Native.to_string(), "native");
+        assert_eq!(SearchKind::Framework.to_string(), "framework");
+    }
+}